@@ -0,0 +1,377 @@
+use pyo3::{pyclass, pyfunction, PyResult};
+
+use crate::{choose_closest, Intersection, Ray};
+
+const LEAF_SIZE: usize = 4;
+
+#[derive(Clone, Copy)]
+struct Aabb {
+    min_x: f32,
+    min_y: f32,
+    max_x: f32,
+    max_y: f32,
+}
+
+impl Aabb {
+    fn union(&self, other: &Aabb) -> Aabb {
+        Aabb {
+            min_x: self.min_x.min(other.min_x),
+            min_y: self.min_y.min(other.min_y),
+            max_x: self.max_x.max(other.max_x),
+            max_y: self.max_y.max(other.max_y),
+        }
+    }
+}
+
+enum Primitive {
+    Line { x1: f32, y1: f32, x2: f32, y2: f32 },
+    Circle { x: f32, y: f32, r: f32 },
+    Capsule { x1: f32, y1: f32, x2: f32, y2: f32, r: f32 },
+}
+
+fn primitive_aabb(primitive: &Primitive) -> Aabb {
+    match primitive {
+        Primitive::Line { x1, y1, x2, y2 } => Aabb {
+            min_x: x1.min(*x2),
+            min_y: y1.min(*y2),
+            max_x: x1.max(*x2),
+            max_y: y1.max(*y2),
+        },
+        Primitive::Circle { x, y, r } => Aabb {
+            min_x: x - r,
+            min_y: y - r,
+            max_x: x + r,
+            max_y: y + r,
+        },
+        Primitive::Capsule { x1, y1, x2, y2, r } => Aabb {
+            min_x: x1.min(*x2) - r,
+            min_y: y1.min(*y2) - r,
+            max_x: x1.max(*x2) + r,
+            max_y: y1.max(*y2) + r,
+        },
+    }
+}
+
+fn primitive_centroid(primitive: &Primitive) -> (f32, f32) {
+    match primitive {
+        Primitive::Line { x1, y1, x2, y2 } => ((x1 + x2) / 2f32, (y1 + y2) / 2f32),
+        Primitive::Circle { x, y, .. } => (*x, *y),
+        Primitive::Capsule { x1, y1, x2, y2, .. } => ((x1 + x2) / 2f32, (y1 + y2) / 2f32),
+    }
+}
+
+fn intersect_primitive(
+    primitive: &Primitive,
+    ray_begin_x: f32,
+    ray_begin_y: f32,
+    ray_len: f32,
+    ray_angle: f32,
+) -> Option<Intersection> {
+    match primitive {
+        Primitive::Line { x1, y1, x2, y2 } => {
+            crate::intersection_line(ray_begin_x, ray_begin_y, ray_len, ray_angle, *x1, *y1, *x2, *y2)
+                .ok()
+                .flatten()
+        }
+        Primitive::Circle { x, y, r } => {
+            crate::intersection_circle(ray_begin_x, ray_begin_y, ray_len, ray_angle, *x, *y, *r)
+                .ok()
+                .flatten()
+        }
+        Primitive::Capsule { x1, y1, x2, y2, r } => crate::intersection_capsule(
+            ray_begin_x,
+            ray_begin_y,
+            ray_len,
+            ray_angle,
+            *x1,
+            *y1,
+            *x2,
+            *y2,
+            *r,
+        )
+        .ok()
+        .flatten(),
+    }
+}
+
+struct BvhNode {
+    aabb: Aabb,
+    left: usize,
+    right: usize,
+    start: usize,
+    count: usize,
+}
+
+impl BvhNode {
+    fn is_leaf(&self) -> bool {
+        self.count > 0
+    }
+}
+
+fn build_node(primitives: &mut [Primitive], start: usize, end: usize, nodes: &mut Vec<BvhNode>) -> usize {
+    let aabb = (start..end)
+        .map(|i| primitive_aabb(&primitives[i]))
+        .reduce(|a, b| a.union(&b))
+        .unwrap();
+
+    let count = end - start;
+    if count <= LEAF_SIZE {
+        nodes.push(BvhNode {
+            aabb,
+            left: 0,
+            right: 0,
+            start,
+            count,
+        });
+        return nodes.len() - 1;
+    }
+
+    let axis_x = (aabb.max_x - aabb.min_x) >= (aabb.max_y - aabb.min_y);
+    let mid = start + count / 2;
+    primitives[start..end].select_nth_unstable_by(count / 2, |a, b| {
+        let (ca, cb) = (primitive_centroid(a), primitive_centroid(b));
+        let (va, vb) = if axis_x { (ca.0, cb.0) } else { (ca.1, cb.1) };
+        va.partial_cmp(&vb).unwrap()
+    });
+
+    let node_index = nodes.len();
+    nodes.push(BvhNode {
+        aabb,
+        left: 0,
+        right: 0,
+        start: 0,
+        count: 0,
+    });
+
+    let left = build_node(primitives, start, mid, nodes);
+    let right = build_node(primitives, mid, end, nodes);
+    nodes[node_index].left = left;
+    nodes[node_index].right = right;
+
+    node_index
+}
+
+fn slab_tmin(aabb: &Aabb, ox: f32, oy: f32, inv_dx: f32, inv_dy: f32, ray_len: f32) -> Option<f32> {
+    let tx1 = (aabb.min_x - ox) * inv_dx;
+    let tx2 = (aabb.max_x - ox) * inv_dx;
+    let ty1 = (aabb.min_y - oy) * inv_dy;
+    let ty2 = (aabb.max_y - oy) * inv_dy;
+
+    let tmin = tx1.min(tx2).max(ty1.min(ty2)).max(0f32);
+    let tmax = tx1.max(tx2).min(ty1.max(ty2)).min(ray_len);
+
+    if tmin > tmax {
+        None
+    } else {
+        Some(tmin)
+    }
+}
+
+/// Opaque precomputed scene: a BVH over line/circle/capsule bounding boxes so `cast_rays`
+/// can reuse it across frames instead of rescanning every primitive per ray.
+#[pyclass]
+pub struct Scene {
+    primitives: Vec<Primitive>,
+    nodes: Vec<BvhNode>,
+}
+
+impl Scene {
+    fn intersect_nearest(
+        &self,
+        ray_begin_x: f32,
+        ray_begin_y: f32,
+        ray_len: f32,
+        ray_angle: f32,
+        view_rect: Option<&crate::Rect>,
+    ) -> Option<Intersection> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        let inv_dx = 1f32 / crate::ops::cos(ray_angle);
+        let inv_dy = 1f32 / crate::ops::sin(ray_angle);
+
+        let mut best: Option<Intersection> = None;
+        let mut stack = vec![0usize];
+
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+
+            if let Some(rect) = view_rect {
+                let aabb = &node.aabb;
+                if !rect.overlaps_aabb(aabb.min_x, aabb.min_y, aabb.max_x, aabb.max_y) {
+                    continue;
+                }
+            }
+
+            let tmin = match slab_tmin(&node.aabb, ray_begin_x, ray_begin_y, inv_dx, inv_dy, ray_len) {
+                Some(tmin) => tmin,
+                None => continue,
+            };
+
+            if let Some(best) = &best {
+                if tmin > best.len {
+                    continue;
+                }
+            }
+
+            if node.is_leaf() {
+                for i in node.start..node.start + node.count {
+                    let hit = intersect_primitive(
+                        &self.primitives[i],
+                        ray_begin_x,
+                        ray_begin_y,
+                        ray_len,
+                        ray_angle,
+                    );
+                    best = choose_closest(best, hit);
+                }
+                continue;
+            }
+
+            let left = &self.nodes[node.left];
+            let right = &self.nodes[node.right];
+            let left_tmin = slab_tmin(&left.aabb, ray_begin_x, ray_begin_y, inv_dx, inv_dy, ray_len);
+            let right_tmin = slab_tmin(&right.aabb, ray_begin_x, ray_begin_y, inv_dx, inv_dy, ray_len);
+
+            match (left_tmin, right_tmin) {
+                (Some(lt), Some(rt)) if lt <= rt => {
+                    stack.push(node.right);
+                    stack.push(node.left);
+                }
+                (Some(_), Some(_)) => {
+                    stack.push(node.left);
+                    stack.push(node.right);
+                }
+                (Some(_), None) => stack.push(node.left),
+                (None, Some(_)) => stack.push(node.right),
+                (None, None) => {}
+            }
+        }
+
+        best
+    }
+}
+
+#[pyfunction]
+pub fn build_scene(
+    lines: Vec<(f32, f32, f32, f32)>,
+    circles: Vec<(f32, f32, f32)>,
+    capsules: Vec<(f32, f32, f32, f32, f32)>,
+) -> Scene {
+    let mut primitives = Vec::with_capacity(lines.len() + circles.len() + capsules.len());
+
+    for (x1, y1, x2, y2) in lines {
+        primitives.push(Primitive::Line { x1, y1, x2, y2 });
+    }
+    for (x, y, r) in circles {
+        primitives.push(Primitive::Circle { x, y, r });
+    }
+    for (x1, y1, x2, y2, r) in capsules {
+        primitives.push(Primitive::Capsule { x1, y1, x2, y2, r });
+    }
+
+    let mut nodes = Vec::new();
+    let primitive_count = primitives.len();
+    if primitive_count > 0 {
+        build_node(&mut primitives, 0, primitive_count, &mut nodes);
+    }
+
+    Scene { primitives, nodes }
+}
+
+#[pyfunction]
+pub fn cast_rays(
+    scene: &Scene,
+    view_angle: f32,
+    fov: f32,
+    rays_count: usize,
+    ray_begin_x: f32,
+    ray_begin_y: f32,
+    ray_len: f32,
+    view_rect: Option<crate::Rect>,
+) -> PyResult<Vec<Ray>> {
+    let mut rays = Vec::with_capacity(rays_count);
+    let angle_offset = view_angle - fov / 2.0;
+
+    for i in 0..rays_count {
+        let angle = i as f32 / rays_count as f32 * fov + angle_offset;
+
+        let (entry, clipped_len) = match &view_rect {
+            Some(rect) => match rect.clip_ray(ray_begin_x, ray_begin_y, ray_len, angle) {
+                Some(bounds) => bounds,
+                None => {
+                    rays.push(Ray::new(ray_begin_x, ray_begin_y, angle, None));
+                    continue;
+                }
+            },
+            None => (0f32, ray_len),
+        };
+
+        let intersection = scene
+            .intersect_nearest(ray_begin_x, ray_begin_y, clipped_len, angle, view_rect.as_ref())
+            .filter(|intersection| intersection.len >= entry);
+        rays.push(Ray::new(ray_begin_x, ray_begin_y, angle, intersection));
+    }
+
+    Ok(rays)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn brute_force_nearest(
+        lines: &[(f32, f32, f32, f32)],
+        circles: &[(f32, f32, f32)],
+        ray_begin_x: f32,
+        ray_begin_y: f32,
+        ray_len: f32,
+        ray_angle: f32,
+    ) -> Option<Intersection> {
+        let line_hit =
+            crate::intersection_lines(ray_begin_x, ray_begin_y, ray_len, ray_angle, lines.to_vec()).unwrap();
+        let circle_hit =
+            crate::intersection_circles(ray_begin_x, ray_begin_y, ray_len, ray_angle, circles.to_vec()).unwrap();
+
+        choose_closest(line_hit, circle_hit)
+    }
+
+    #[test]
+    fn bvh_traversal_matches_brute_force() {
+        let lines = vec![
+            (10.0, -5.0, 10.0, 5.0),
+            (-20.0, -20.0, 20.0, -20.0),
+            (0.0, 30.0, 5.0, 35.0),
+        ];
+        let circles = vec![(0.0, 0.0, 2.0), (15.0, 15.0, 3.0), (-30.0, 10.0, 4.0)];
+
+        let scene = build_scene(lines.clone(), circles.clone(), Vec::new());
+
+        for i in 0..36 {
+            let angle = i as f32 * (std::f32::consts::TAU / 36.0);
+            let expected = brute_force_nearest(&lines, &circles, 0.0, 0.0, 50.0, angle);
+            let actual = scene.intersect_nearest(0.0, 0.0, 50.0, angle, None);
+
+            match (expected, actual) {
+                (Some(e), Some(a)) => {
+                    assert!((e.len - a.len).abs() < 1e-3, "angle {angle}: expected len {}, got {}", e.len, a.len)
+                }
+                (None, None) => {}
+                (Some(e), None) => panic!("angle {angle}: expected hit len {} but BVH found none", e.len),
+                (None, Some(a)) => panic!("angle {angle}: BVH found hit len {} but brute force found none", a.len),
+            }
+        }
+    }
+
+    #[test]
+    fn build_scene_includes_capsules() {
+        let capsules = vec![(0.0, -5.0, 0.0, 5.0, 1.0)];
+        let scene = build_scene(Vec::new(), Vec::new(), capsules);
+
+        let hit = scene
+            .intersect_nearest(-5.0, 0.0, 20.0, 0.0, None)
+            .expect("ray should hit the capsule through the BVH fast path");
+        assert!((hit.len - 4.0).abs() < 1e-3);
+    }
+}
@@ -1,18 +1,28 @@
-use once_cell::sync::Lazy;
+use std::sync::mpsc::channel;
+use std::sync::Arc;
+
+use once_cell::sync::OnceCell;
 use pyo3::prelude::PyModule;
 use pyo3::{pyclass, pyfunction, pymethods, pymodule, wrap_pyfunction, PyResult, Python};
 use threadpool::ThreadPool;
 
-const NUM_THREADS: usize = 4;
+mod bvh;
+mod ops;
+
+const DEFAULT_NUM_THREADS: usize = 4;
+
+static THREAD_POOL: OnceCell<ThreadPool> = OnceCell::new();
 
-static THREAD_POOL: Lazy<ThreadPool> = Lazy::new(|| ThreadPool::new(NUM_THREADS));
+fn thread_pool() -> &'static ThreadPool {
+    THREAD_POOL.get_or_init(|| ThreadPool::new(DEFAULT_NUM_THREADS))
+}
 
 #[pyclass]
 #[derive(Clone)]
-struct Intersection {
+pub(crate) struct Intersection {
     x: f32,
     y: f32,
-    len: f32,
+    pub(crate) len: f32,
 }
 
 #[pymethods]
@@ -39,7 +49,7 @@ impl Intersection {
 }
 
 #[pyclass]
-struct Ray {
+pub(crate) struct Ray {
     x: f32,
     y: f32,
     angle: f32,
@@ -49,7 +59,7 @@ struct Ray {
 #[pymethods]
 impl Ray {
     #[new]
-    fn new(x: f32, y: f32, angle: f32, intersection: Option<Intersection>) -> Self {
+    pub(crate) fn new(x: f32, y: f32, angle: f32, intersection: Option<Intersection>) -> Self {
         Self {
             x,
             y,
@@ -82,11 +92,111 @@ impl Ray {
     }
 }
 
+/// An axis-aligned viewport used to cull geometry and clip rays to what's
+/// actually visible.
+#[pyclass]
+#[derive(Clone, Copy)]
+pub(crate) struct Rect {
+    x: f32,
+    y: f32,
+    w: f32,
+    h: f32,
+}
+
+#[pymethods]
+impl Rect {
+    #[new]
+    fn new(x: f32, y: f32, w: f32, h: f32) -> Self {
+        Rect { x, y, w, h }
+    }
+
+    #[getter]
+    fn x(&self) -> f32 {
+        self.x
+    }
+
+    #[getter]
+    fn y(&self) -> f32 {
+        self.y
+    }
+
+    #[getter]
+    fn w(&self) -> f32 {
+        self.w
+    }
+
+    #[getter]
+    fn h(&self) -> f32 {
+        self.h
+    }
+}
+
+impl Rect {
+    pub(crate) fn overlaps_aabb(&self, min_x: f32, min_y: f32, max_x: f32, max_y: f32) -> bool {
+        self.x <= max_x && self.x + self.w >= min_x && self.y <= max_y && self.y + self.h >= min_y
+    }
+
+    /// Liang–Barsky slab clip of the ray against the rect's four edges.
+    /// Returns the `(entry, exit)` distances along the ray, both bounded by
+    /// `ray_len`, or `None` if the ray never enters the rect (`tmin > tmax`).
+    /// Callers must discard any hit nearer than `entry` — it lies outside the
+    /// rect, before the ray has actually entered it.
+    pub(crate) fn clip_ray(&self, ray_begin_x: f32, ray_begin_y: f32, ray_len: f32, ray_angle: f32) -> Option<(f32, f32)> {
+        let dir_x = ops::cos(ray_angle);
+        let dir_y = ops::sin(ray_angle);
+
+        let mut tmin = 0f32;
+        let mut tmax = ray_len;
+
+        if dir_x.abs() < f32::EPSILON {
+            if ray_begin_x < self.x || ray_begin_x > self.x + self.w {
+                return None;
+            }
+        } else {
+            let inv_dx = 1f32 / dir_x;
+            let mut t1 = (self.x - ray_begin_x) * inv_dx;
+            let mut t2 = (self.x + self.w - ray_begin_x) * inv_dx;
+            if t1 > t2 {
+                std::mem::swap(&mut t1, &mut t2);
+            }
+            tmin = tmin.max(t1);
+            tmax = tmax.min(t2);
+        }
+
+        if dir_y.abs() < f32::EPSILON {
+            if ray_begin_y < self.y || ray_begin_y > self.y + self.h {
+                return None;
+            }
+        } else {
+            let inv_dy = 1f32 / dir_y;
+            let mut t1 = (self.y - ray_begin_y) * inv_dy;
+            let mut t2 = (self.y + self.h - ray_begin_y) * inv_dy;
+            if t1 > t2 {
+                std::mem::swap(&mut t1, &mut t2);
+            }
+            tmin = tmin.max(t1);
+            tmax = tmax.min(t2);
+        }
+
+        if tmin > tmax {
+            None
+        } else {
+            Some((tmin, tmax))
+        }
+    }
+}
+
 #[pyfunction]
-fn init() {
-    Lazy::force(&THREAD_POOL);
+fn init(num_threads: usize) {
+    THREAD_POOL.get_or_init(|| ThreadPool::new(num_threads.max(1)));
 }
 
+/// A ray angle paired with its (possibly failed) intersection lookup.
+type RayHit = (f32, PyResult<Option<Intersection>>);
+/// What a single worker thread reports back for its chunk: the rays it
+/// computed, or the panic it hit while computing them.
+type ChunkResult = PyResult<Vec<RayHit>>;
+
 #[pyfunction]
 fn rays(
     view_angle: f32,
@@ -97,31 +207,118 @@ fn rays(
     ray_len: f32,
     lines: Vec<(f32, f32, f32, f32)>,
     circles: Vec<(f32, f32, f32)>,
-    circles_accuracy: f32,
+    capsules: Vec<(f32, f32, f32, f32, f32)>,
+    view_rect: Option<Rect>,
 ) -> PyResult<Vec<Ray>> {
-    let mut rays = Vec::<Ray>::with_capacity(rays_count);
+    let pool = thread_pool();
+    let num_threads = pool.max_count().max(1);
 
     let angle_offset = view_angle - fov / 2.0;
+    let chunk_size = rays_count.div_ceil(num_threads);
+
+    let (lines, circles, capsules) = match &view_rect {
+        Some(rect) => (
+            lines
+                .into_iter()
+                .filter(|&(x1, y1, x2, y2)| {
+                    rect.overlaps_aabb(x1.min(x2), y1.min(y2), x1.max(x2), y1.max(y2))
+                })
+                .collect(),
+            circles
+                .into_iter()
+                .filter(|&(x, y, r)| rect.overlaps_aabb(x - r, y - r, x + r, y + r))
+                .collect(),
+            capsules
+                .into_iter()
+                .filter(|&(x1, y1, x2, y2, r)| {
+                    rect.overlaps_aabb(x1.min(x2) - r, y1.min(y2) - r, x1.max(x2) + r, y1.max(y2) + r)
+                })
+                .collect(),
+        ),
+        None => (lines, circles, capsules),
+    };
 
-    for i in 0..rays_count {
-        let angle = i as f32 / rays_count as f32 * fov + angle_offset;
+    let lines = Arc::new(lines);
+    let circles = Arc::new(circles);
+    let capsules = Arc::new(capsules);
+
+    let (sender, receiver) = channel();
+    let mut chunk_count = 0;
+    let mut start = 0;
+
+    while start < rays_count {
+        let end = (start + chunk_size).min(rays_count);
+        let sender = sender.clone();
+        let lines = Arc::clone(&lines);
+        let circles = Arc::clone(&circles);
+        let capsules = Arc::clone(&capsules);
+        chunk_count += 1;
+
+        pool.execute(move || {
+            let outcome = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                let mut chunk_rays = Vec::with_capacity(end - start);
+
+                for i in start..end {
+                    let angle = i as f32 / rays_count as f32 * fov + angle_offset;
+
+                    let (entry, clipped_len) = match &view_rect {
+                        Some(rect) => match rect.clip_ray(ray_begin_x, ray_begin_y, ray_len, angle) {
+                            Some(bounds) => bounds,
+                            None => {
+                                chunk_rays.push((angle, Ok(None)));
+                                continue;
+                            }
+                        },
+                        None => (0f32, ray_len),
+                    };
+
+                    let intersection_result = intersection(
+                        ray_begin_x,
+                        ray_begin_y,
+                        clipped_len,
+                        angle,
+                        (*lines).clone(),
+                        (*circles).clone(),
+                        (*capsules).clone(),
+                    )
+                    .map(|hit| hit.filter(|intersection| intersection.len >= entry));
+
+                    chunk_rays.push((angle, intersection_result));
+                }
 
-        let intersection_result = intersection(
-            ray_begin_x,
-            ray_begin_y,
-            ray_len,
-            angle,
-            lines.clone(),
-            circles.clone(),
-            circles_accuracy,
-        );
+                chunk_rays
+            }))
+            .map_err(|payload| {
+                let message = payload
+                    .downcast_ref::<&str>()
+                    .map(|s| s.to_string())
+                    .or_else(|| payload.downcast_ref::<String>().cloned())
+                    .unwrap_or_else(|| "ray worker thread panicked".to_string());
+                pyo3::exceptions::PyRuntimeError::new_err(message)
+            });
+
+            // The main thread waits for every chunk before it looks at any
+            // error, so the receiver is still alive here; the result is ignored.
+            let _ = sender.send((start, outcome));
+        });
+
+        start = end;
+    }
 
-        let intersection = match intersection_result {
-            Ok(value) => value,
-            Err(error) => return Err(error),
-        };
+    let mut chunks: Vec<(usize, ChunkResult)> = receiver.iter().take(chunk_count).collect();
+    chunks.sort_by_key(|(start, _)| *start);
 
-        rays.push(Ray::new(ray_begin_x, ray_begin_y, angle, intersection));
+    let mut rays = Vec::<Ray>::with_capacity(rays_count);
+
+    for (_, chunk) in chunks {
+        for (angle, intersection_result) in chunk? {
+            let intersection = match intersection_result {
+                Ok(value) => value,
+                Err(error) => return Err(error),
+            };
+
+            rays.push(Ray::new(ray_begin_x, ray_begin_y, angle, intersection));
+        }
     }
 
     Ok(rays)
@@ -135,7 +332,7 @@ fn intersection(
     ray_angle: f32,
     lines: Vec<(f32, f32, f32, f32)>,
     circles: Vec<(f32, f32, f32)>,
-    circles_accuracy: f32,
+    capsules: Vec<(f32, f32, f32, f32, f32)>,
 ) -> PyResult<Option<Intersection>> {
     let intersect_lines_result =
         intersection_lines(ray_begin_x, ray_begin_y, ray_len, ray_angle, lines);
@@ -145,21 +342,24 @@ fn intersection(
         _ => return intersect_lines_result,
     };
 
-    let intersect_circles_result = intersection_circles(
-        ray_begin_x,
-        ray_begin_y,
-        ray_len,
-        ray_angle,
-        circles,
-        circles_accuracy,
-    );
+    let intersect_circles_result =
+        intersection_circles(ray_begin_x, ray_begin_y, ray_len, ray_angle, circles);
 
     let intersect_circles = match intersect_circles_result {
         Ok(value) => value,
         _ => return intersect_circles_result,
     };
 
+    let intersect_capsules_result =
+        intersection_capsules(ray_begin_x, ray_begin_y, ray_len, ray_angle, capsules);
+
+    let intersect_capsules = match intersect_capsules_result {
+        Ok(value) => value,
+        _ => return intersect_capsules_result,
+    };
+
     let closest = choose_closest(intersect_lines, intersect_circles);
+    let closest = choose_closest(closest, intersect_capsules);
 
     return Ok(closest);
 }
@@ -198,7 +398,7 @@ fn intersection_lines(
 }
 
 #[pyfunction]
-fn intersection_line(
+pub(crate) fn intersection_line(
     ray_begin_x: f32,
     ray_begin_y: f32,
     ray_len: f32,
@@ -208,55 +408,34 @@ fn intersection_line(
     line_x2: f32,
     line_y2: f32,
 ) -> PyResult<Option<Intersection>> {
-    let ray_end_x = ray_angle.cos() * ray_len + ray_begin_x;
-    let ray_end_y = ray_angle.sin() * ray_len + ray_begin_y;
-
-    let ray_x_diff = ray_end_x - ray_begin_x;
-    let ray_tg = (ray_end_y - ray_begin_y) / ray_x_diff;
-    let ray_b = ray_begin_y - ray_tg * ray_begin_x;
+    const PARALLEL_EPSILON: f32 = 1e-6;
 
-    let line_x_diff = line_x2 - line_x1;
-    let line_tg = (line_y2 - line_y1) / line_x_diff;
-    let tg_diff = line_tg - ray_tg;
+    let ray_x = ops::cos(ray_angle) * ray_len;
+    let ray_y = ops::sin(ray_angle) * ray_len;
 
-    if tg_diff == 0f32 {
-        return Ok(None);
-    }
-
-    let line_b = line_y1 - line_tg * line_x1;
+    let line_x = line_x2 - line_x1;
+    let line_y = line_y2 - line_y1;
 
-    let y = (ray_b * line_tg - line_b * ray_tg) / tg_diff;
-    let x = (y - ray_b) / ray_tg;
+    let rxs = ray_x * line_y - ray_y * line_x;
 
-    if line_x1.min(line_x2) > x
-        || x > line_x1.max(line_x2)
-        || line_y1.min(line_y2) > y
-        || y > line_y1.max(line_y2)
-    {
+    if rxs.abs() < PARALLEL_EPSILON {
         return Ok(None);
     }
 
-    let len = vector_len(ray_begin_x, ray_begin_y, x, y);
+    let to_line_x = line_x1 - ray_begin_x;
+    let to_line_y = line_y1 - ray_begin_y;
 
-    if len > ray_len {
-        return Ok(None);
-    }
+    let t = (to_line_x * line_y - to_line_y * line_x) / rxs;
+    let u = (to_line_x * ray_y - to_line_y * ray_x) / rxs;
 
-    let cos = vectors_cos(
-        ray_begin_x,
-        ray_begin_y,
-        ray_end_x,
-        ray_end_y,
-        x,
-        y,
-        len,
-        ray_len,
-    );
-
-    if cos.is_sign_negative() {
+    if !(0f32..=1f32).contains(&t) || !(0f32..=1f32).contains(&u) {
         return Ok(None);
     }
 
+    let x = ray_begin_x + t * ray_x;
+    let y = ray_begin_y + t * ray_y;
+    let len = t * ray_len;
+
     return Ok(Some(Intersection { x, y, len }));
 }
 
@@ -267,7 +446,6 @@ fn intersection_circles(
     ray_len: f32,
     ray_angle: f32,
     circles: Vec<(f32, f32, f32)>,
-    accuracy: f32,
 ) -> PyResult<Option<Intersection>> {
     let mut closest: Option<Intersection> = None;
 
@@ -280,8 +458,6 @@ fn intersection_circles(
             circle_x,
             circle_y,
             circle_radius,
-            accuracy,
-            0f32,
         );
 
         let intersect = match intersect_result {
@@ -295,8 +471,56 @@ fn intersection_circles(
     return Ok(closest);
 }
 
+/// Analytic ray/circle intersection: solves `|O + tD - C|^2 = R^2` for the unit
+/// ray direction `D`, returning the nearest forward root as the real surface
+/// hit point (constant-time, unlike `intersection_circle_marching` below).
+#[pyfunction]
+pub(crate) fn intersection_circle(
+    ray_begin_x: f32,
+    ray_begin_y: f32,
+    ray_len: f32,
+    ray_angle: f32,
+    circle_x: f32,
+    circle_y: f32,
+    circle_radius: f32,
+) -> PyResult<Option<Intersection>> {
+    let dir_x = ops::cos(ray_angle);
+    let dir_y = ops::sin(ray_angle);
+
+    let to_center_x = ray_begin_x - circle_x;
+    let to_center_y = ray_begin_y - circle_y;
+
+    let b = 2f32 * (to_center_x * dir_x + to_center_y * dir_y);
+    let c = to_center_x * to_center_x + to_center_y * to_center_y - circle_radius * circle_radius;
+
+    let disc = b * b - 4f32 * c;
+    if disc < 0f32 {
+        return Ok(None);
+    }
+
+    let sqrt_disc = ops::sqrt(disc);
+    let mut len = (-b - sqrt_disc) / 2f32;
+    if len < 0f32 {
+        len = (-b + sqrt_disc) / 2f32;
+    }
+
+    if len < 0f32 || len > ray_len {
+        return Ok(None);
+    }
+
+    let x = ray_begin_x + dir_x * len;
+    let y = ray_begin_y + dir_y * len;
+
+    return Ok(Some(Intersection { x, y, len }));
+}
+
+/// Sphere-marches along the ray until it gets within `accuracy` of the circle's
+/// surface. Kept around for callers that want to trade exactness for a tunable
+/// accuracy/step budget; `intersection_circles` uses the analytic
+/// `intersection_circle` instead. The returned point is the last marched
+/// position, which lies within `accuracy` of the surface rather than on it.
 #[pyfunction]
-fn intersection_circle(
+pub(crate) fn intersection_circle_marching(
     ray_begin_x: f32,
     ray_begin_y: f32,
     ray_len: f32,
@@ -327,11 +551,11 @@ fn intersection_circle(
         }));
     }
 
-    let next_x = ray_angle.cos() * len_to_circle + ray_begin_x;
-    let next_y = ray_angle.sin() * len_to_circle + ray_begin_y;
+    let next_x = ops::cos(ray_angle) * len_to_circle + ray_begin_x;
+    let next_y = ops::sin(ray_angle) * len_to_circle + ray_begin_y;
     len += len_to_circle;
 
-    intersection_circle(
+    intersection_circle_marching(
         next_x,
         next_y,
         ray_len,
@@ -344,15 +568,124 @@ fn intersection_circle(
     )
 }
 
-fn vectors_cos(x: f32, y: f32, x1: f32, y1: f32, x2: f32, y2: f32, len1: f32, len2: f32) -> f32 {
-    ((x - x1) * (x - x2) + (y - y1) * (y - y2)) / (len1 * len2)
+#[pyfunction]
+fn intersection_capsules(
+    ray_begin_x: f32,
+    ray_begin_y: f32,
+    ray_len: f32,
+    ray_angle: f32,
+    capsules: Vec<(f32, f32, f32, f32, f32)>,
+) -> PyResult<Option<Intersection>> {
+    let mut closest: Option<Intersection> = None;
+
+    for (x1, y1, x2, y2, radius) in capsules {
+        let intersect_result = intersection_capsule(
+            ray_begin_x,
+            ray_begin_y,
+            ray_len,
+            ray_angle,
+            x1,
+            y1,
+            x2,
+            y2,
+            radius,
+        );
+
+        let intersect = match intersect_result {
+            Ok(value) => value,
+            _ => return intersect_result,
+        };
+
+        closest = choose_closest(closest, intersect);
+    }
+
+    return Ok(closest);
+}
+
+/// Intersects a ray with a capsule: a segment `(x1,y1)-(x2,y2)` inflated by
+/// `radius`, i.e. a rectangle with two circular end caps. The straight body is
+/// solved as the infinite cylinder around the segment's axis, clamped to the
+/// part of that axis the segment actually covers; anything that falls off
+/// either end is handled by the end-cap circles instead.
+#[pyfunction]
+pub(crate) fn intersection_capsule(
+    ray_begin_x: f32,
+    ray_begin_y: f32,
+    ray_len: f32,
+    ray_angle: f32,
+    x1: f32,
+    y1: f32,
+    x2: f32,
+    y2: f32,
+    radius: f32,
+) -> PyResult<Option<Intersection>> {
+    let dir_x = ops::cos(ray_angle);
+    let dir_y = ops::sin(ray_angle);
+
+    let axis_x = x2 - x1;
+    let axis_y = y2 - y1;
+    let axis_len_sq = axis_x * axis_x + axis_y * axis_y;
+
+    let mut body_hit: Option<Intersection> = None;
+
+    if axis_len_sq > f32::EPSILON {
+        let to_begin_x = ray_begin_x - x1;
+        let to_begin_y = ray_begin_y - y1;
+
+        // 2D cross products of the ray direction/origin against the axis: the
+        // perpendicular distance from a ray point to the infinite line through
+        // the axis is `|cross| / sqrt(axis_len_sq)`, so squaring and setting
+        // it equal to `radius` gives the cylinder-body quadratic below.
+        let cross_dir = dir_x * axis_y - dir_y * axis_x;
+        let cross_origin = to_begin_x * axis_y - to_begin_y * axis_x;
+
+        let a = cross_dir * cross_dir;
+        let b = 2f32 * cross_dir * cross_origin;
+        let c = cross_origin * cross_origin - radius * radius * axis_len_sq;
+
+        let disc = b * b - a * c * 4f32;
+
+        if a > f32::EPSILON && disc >= 0f32 {
+            let sqrt_disc = ops::sqrt(disc);
+            let mut len = (-b - sqrt_disc) / (2f32 * a);
+            if len < 0f32 {
+                len = (-b + sqrt_disc) / (2f32 * a);
+            }
+
+            if len >= 0f32 && len <= ray_len {
+                let x = ray_begin_x + dir_x * len;
+                let y = ray_begin_y + dir_y * len;
+                let foot = ((x - x1) * axis_x + (y - y1) * axis_y) / axis_len_sq;
+
+                if (0f32..=1f32).contains(&foot) {
+                    body_hit = Some(Intersection { x, y, len });
+                }
+            }
+        }
+    }
+
+    let cap1_result = intersection_circle(ray_begin_x, ray_begin_y, ray_len, ray_angle, x1, y1, radius);
+    let cap1 = match cap1_result {
+        Ok(value) => value,
+        _ => return cap1_result,
+    };
+
+    let cap2_result = intersection_circle(ray_begin_x, ray_begin_y, ray_len, ray_angle, x2, y2, radius);
+    let cap2 = match cap2_result {
+        Ok(value) => value,
+        _ => return cap2_result,
+    };
+
+    let caps_hit = choose_closest(cap1, cap2);
+
+    return Ok(choose_closest(body_hit, caps_hit));
 }
 
 fn vector_len(x1: f32, y1: f32, x2: f32, y2: f32) -> f32 {
-    ((x1 - x2).powi(2) + (y1 - y2).powi(2)).sqrt()
+    ops::sqrt(ops::square(x1 - x2) + ops::square(y1 - y2))
 }
 
-fn choose_closest(
+pub(crate) fn choose_closest(
     first: Option<Intersection>,
     second: Option<Intersection>,
 ) -> Option<Intersection> {
@@ -374,6 +707,7 @@ fn choose_closest(
 #[pymodule]
 fn caster(_py: Python, module: &PyModule) -> PyResult<()> {
     module.add_class::<Intersection>().unwrap();
+    module.add_class::<Rect>().unwrap();
     module
         .add_function(wrap_pyfunction!(intersection, module).unwrap())
         .unwrap();
@@ -389,11 +723,94 @@ fn caster(_py: Python, module: &PyModule) -> PyResult<()> {
     module
         .add_function(wrap_pyfunction!(intersection_circle, module).unwrap())
         .unwrap();
+    module
+        .add_function(wrap_pyfunction!(intersection_circle_marching, module).unwrap())
+        .unwrap();
+    module
+        .add_function(wrap_pyfunction!(intersection_capsules, module).unwrap())
+        .unwrap();
+    module
+        .add_function(wrap_pyfunction!(intersection_capsule, module).unwrap())
+        .unwrap();
     module
         .add_function(wrap_pyfunction!(rays, module).unwrap())
         .unwrap();
     module
         .add_function(wrap_pyfunction!(init, module).unwrap())
         .unwrap();
+    module.add_class::<bvh::Scene>().unwrap();
+    module
+        .add_function(wrap_pyfunction!(bvh::build_scene, module).unwrap())
+        .unwrap();
+    module
+        .add_function(wrap_pyfunction!(bvh::cast_rays, module).unwrap())
+        .unwrap();
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 1e-3;
+
+    #[test]
+    fn intersection_circle_hits_from_outside() {
+        let hit = intersection_circle(0.0, 0.0, 10.0, 0.0, 5.0, 0.0, 1.0)
+            .unwrap()
+            .expect("ray should hit the circle");
+        assert!((hit.len - 4.0).abs() < EPSILON);
+        assert!((hit.x - 4.0).abs() < EPSILON);
+        assert!((hit.y - 0.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn intersection_circle_from_inside_returns_exit_point() {
+        let hit = intersection_circle(0.0, 0.0, 10.0, 0.0, 0.0, 0.0, 2.0)
+            .unwrap()
+            .expect("ray starting inside the circle should hit its far edge");
+        assert!((hit.len - 2.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn intersection_line_handles_vertical_wall() {
+        // The old slope-intercept line solver divided by zero on a wall like
+        // this one; the parametric cross-product form has no such asymptote.
+        let hit = intersection_line(0.0, 0.0, 10.0, 0.0, 5.0, -5.0, 5.0, 5.0)
+            .unwrap()
+            .expect("ray should hit the vertical wall");
+        assert!((hit.len - 5.0).abs() < EPSILON);
+        assert!((hit.x - 5.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn intersection_capsule_hits_straight_body() {
+        let hit = intersection_capsule(-5.0, 0.0, 100.0, 0.0, 0.0, -5.0, 0.0, 5.0, 1.0)
+            .unwrap()
+            .expect("ray should hit the capsule's straight body");
+        assert!((hit.len - 4.0).abs() < EPSILON);
+        assert!((hit.x - (-1.0)).abs() < EPSILON);
+    }
+
+    #[test]
+    fn intersection_capsule_falls_back_to_end_cap() {
+        // Aimed past the segment's end, where the body's foot parameter falls
+        // outside [0, 1] and only the rounded end cap can still catch the hit.
+        let hit = intersection_capsule(
+            6.0,
+            20.0,
+            100.0,
+            -std::f32::consts::FRAC_PI_2,
+            -5.0,
+            0.0,
+            5.0,
+            0.0,
+            1.0,
+        )
+        .unwrap()
+        .expect("ray should hit the capsule's rounded end cap");
+        assert!((hit.len - 20.0).abs() < EPSILON);
+        assert!((hit.x - 6.0).abs() < EPSILON);
+        assert!((hit.y - 0.0).abs() < EPSILON);
+    }
+}
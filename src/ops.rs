@@ -0,0 +1,44 @@
+//! Deterministic math primitives for the hot intersection paths.
+//!
+//! `f32::sin`/`cos`/`sqrt` dispatch to whatever libm the target ships with, so
+//! their exact bit patterns can differ across platforms and Rust versions —
+//! a problem for lockstep multiplayer, replays, or golden-image tests of a
+//! raycaster. Builds with the `libm` feature route through the pure-Rust
+//! `libm` crate instead, which gives bit-identical results everywhere.
+
+#[cfg(feature = "libm")]
+pub(crate) fn sin(x: f32) -> f32 {
+    libm::sinf(x)
+}
+
+#[cfg(not(feature = "libm"))]
+pub(crate) fn sin(x: f32) -> f32 {
+    x.sin()
+}
+
+#[cfg(feature = "libm")]
+pub(crate) fn cos(x: f32) -> f32 {
+    libm::cosf(x)
+}
+
+#[cfg(not(feature = "libm"))]
+pub(crate) fn cos(x: f32) -> f32 {
+    x.cos()
+}
+
+#[cfg(feature = "libm")]
+pub(crate) fn sqrt(x: f32) -> f32 {
+    libm::sqrtf(x)
+}
+
+#[cfg(not(feature = "libm"))]
+pub(crate) fn sqrt(x: f32) -> f32 {
+    x.sqrt()
+}
+
+/// `vector_len` only ever squares its operands, so there's no need for a
+/// general `powi` — explicit multiplication is a single IEEE 754 rounded
+/// product, so it gives the same bits under both the std and `libm` backends.
+pub(crate) fn square(x: f32) -> f32 {
+    x * x
+}